@@ -24,22 +24,67 @@ fn efi_main(
     _image_handle: EfiHandle,
     efi_system_table: &EfiSystemTable,
 ) {
-    let mut vram = init_vram(efi_system_table)
-        .expect("Failed to init vram");
-
-    let vw = vram.width();
-    let vh = vram.height();
-    fill_rect(&mut vram, 0x000000, 0, 0, vw, vh)
-        .expect("fill_rect failed");
-    fill_rect(&mut vram, 0xff0000, 32, 32, 32, 32)
-        .expect("fill_rect failed");
-    fill_rect(&mut vram, 0x00ff00, 64, 64, 64, 64)
-        .expect("fill_rect failed");
-    fill_rect(&mut vram, 0x0000ff, 128, 128, 128, 128)
-        .expect("fill_rect failed");
-
-    for i in 0..256 {
-        let _ = draw_point(&mut vram, 0x010101 * i, i, i);
+    match init_vram(efi_system_table, None)
+        .expect("Failed to init vram")
+    {
+        Display::Gop(mut vram) => {
+            let vw = vram.width();
+            let vh = vram.height();
+            fill_rect(&mut vram, 0x000000, 0, 0, vw, vh)
+                .expect("fill_rect failed");
+            fill_rect(&mut vram, 0xff0000, 32, 32, 32, 32)
+                .expect("fill_rect failed");
+            fill_rect(&mut vram, 0x00ff00, 64, 64, 64, 64)
+                .expect("fill_rect failed");
+            fill_rect(
+                &mut vram, 0x0000ff, 128, 128, 128, 128,
+            )
+            .expect("fill_rect failed");
+
+            for i in 0..256 {
+                let _ =
+                    draw_point(&mut vram, 0x010101 * i, i, i);
+            }
+        }
+        Display::Blt(blt) => {
+            let vw = blt.width();
+            let vh = blt.height();
+            blt.fill_rect_blt(
+                rgb_to_blt_pixel(0x000000),
+                0,
+                0,
+                vw,
+                vh,
+            )
+            .expect("fill_rect_blt failed");
+            blt.fill_rect_blt(
+                rgb_to_blt_pixel(0xff0000),
+                32,
+                32,
+                32,
+                32,
+            )
+            .expect("fill_rect_blt failed");
+            blt.fill_rect_blt(
+                rgb_to_blt_pixel(0x00ff00),
+                64,
+                64,
+                64,
+                64,
+            )
+            .expect("fill_rect_blt failed");
+            blt.fill_rect_blt(
+                rgb_to_blt_pixel(0x0000ff),
+                128,
+                128,
+                128,
+                128,
+            )
+            .expect("fill_rect_blt failed");
+            blt.copy_rect(32, 32, 256, 32, 32, 32)
+                .expect("copy_rect failed");
+        }
+        Display::TextOnly => {}
     }
 
     loop {
@@ -74,11 +119,31 @@ const _: () = assert!(
         == 320
 );
 
+type EfiSimpleTextOutputReset = extern "win64" fn(
+    this: *const EfiVoid,
+    extended_verification: u8,
+) -> EfiStatus;
+
+type EfiSimpleTextOutputString = extern "win64" fn(
+    this: *const EfiVoid,
+    string: *const u16,
+) -> EfiStatus;
+
+#[repr(C)]
+struct EfiSimpleTextOutputProtocol {
+    reset: EfiSimpleTextOutputReset,
+    pub output_string: EfiSimpleTextOutputString,
+}
+
 #[repr(C)]
 struct EfiSystemTable {
-    _reserved0: [u64; 12],
+    _reserved0: [u64; 8],
+    pub con_out: &'static EfiSimpleTextOutputProtocol,
+    _reserved1: [u64; 3],
     pub boot_services: &'static EfiBootServicesTable,
 }
+const _: () =
+    assert!(offset_of!(EfiSystemTable, con_out) == 64);
 const _: () = assert!(
     offset_of!(EfiSystemTable, boot_services) == 96
 );
@@ -102,12 +167,284 @@ const EFI_GRAPHICS_OUTPUT_PROTOCOL_GUID: EfiGuid =
         ],
     };
 
+const EFI_CONSOLE_CONTROL_PROTOCOL_GUID: EfiGuid =
+    EfiGuid {
+        data0: 0xf42f7782,
+        data1: 0x0012,
+        data2: 0x4c12,
+        data3: [
+            0x99, 0x56, 0x49, 0xf9, 0x43, 0x04, 0xf7, 0x21,
+        ],
+    };
+
+// `Text` is only ever read back from firmware via GetMode,
+// never constructed in safe Rust.
+#[allow(dead_code)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u32)]
+enum EfiConsoleControlScreenMode {
+    Text = 0,
+    Graphics = 1,
+}
+
+type EfiConsoleControlGetMode = extern "win64" fn(
+    this: *const EfiVoid,
+    mode: *mut EfiConsoleControlScreenMode,
+    uga_exists: *mut u8,
+    std_in_locked: *mut u8,
+) -> EfiStatus;
+
+type EfiConsoleControlSetMode = extern "win64" fn(
+    this: *const EfiVoid,
+    mode: EfiConsoleControlScreenMode,
+) -> EfiStatus;
+
+type EfiConsoleControlLockStdIn = extern "win64" fn(
+    this: *const EfiVoid,
+    password: *const u16,
+) -> EfiStatus;
+
+#[repr(C)]
+#[derive(Debug)]
+struct EfiConsoleControlProtocol {
+    get_mode: EfiConsoleControlGetMode,
+    set_mode: EfiConsoleControlSetMode,
+    lock_std_in: EfiConsoleControlLockStdIn,
+}
+
+// Never fails the boot: no-ops if the protocol is absent, only
+// warns if SetMode itself fails.
+fn enter_graphics_mode(efi_system_table: &EfiSystemTable) {
+    let mut console_control =
+        null_mut::<EfiConsoleControlProtocol>();
+    let status =
+        (efi_system_table.boot_services.locate_protocol)(
+            &EFI_CONSOLE_CONTROL_PROTOCOL_GUID,
+            null_mut::<EfiVoid>(),
+            &mut console_control
+                as *mut *mut EfiConsoleControlProtocol
+                as *mut *mut EfiVoid,
+        );
+    if status != EfiStatus::Success {
+        return;
+    }
+    let console_control = unsafe { &*console_control };
+    let status = (console_control.set_mode)(
+        console_control as *const EfiConsoleControlProtocol
+            as *const EfiVoid,
+        EfiConsoleControlScreenMode::Graphics,
+    );
+    if status != EfiStatus::Success {
+        write_text_status(
+            efi_system_table,
+            "Warning: failed to switch to graphics mode.\r\n",
+        );
+    }
+}
+
+type EfiGraphicsOutputProtocolQueryMode =
+    extern "win64" fn(
+        this: *const EfiVoid,
+        mode_number: u32,
+        size_of_info: *mut u64,
+        info: *mut *const EfiGraphicsOutputProtocolPixelInfo,
+    ) -> EfiStatus;
+
+type EfiGraphicsOutputProtocolSetMode = extern "win64" fn(
+    this: *const EfiVoid,
+    mode_number: u32,
+) -> EfiStatus;
+
+// VideoToBltBuffer/BltBufferToVideo round-trip through a
+// software buffer; nothing in this crate needs that yet, but
+// they're part of the GOP Blt contract so they stay listed.
+#[allow(dead_code)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u32)]
+enum EfiGraphicsOutputProtocolBltOperation {
+    VideoFill = 0,
+    VideoToBltBuffer = 1,
+    BltBufferToVideo = 2,
+    VideoToVideo = 3,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct EfiGraphicsOutputBltPixel {
+    pub blue: u8,
+    pub green: u8,
+    pub red: u8,
+    pub reserved: u8,
+}
+
+type EfiGraphicsOutputProtocolBlt = extern "win64" fn(
+    this: *const EfiVoid,
+    blt_buffer: *mut EfiGraphicsOutputBltPixel,
+    blt_operation: EfiGraphicsOutputProtocolBltOperation,
+    source_x: usize,
+    source_y: usize,
+    destination_x: usize,
+    destination_y: usize,
+    width: usize,
+    height: usize,
+    delta: usize,
+) -> EfiStatus;
+
 #[repr(C)]
 #[derive(Debug)]
 struct EfiGraphicsOutputProtocol<'a> {
-    reserved: [u64; 3],
+    query_mode: EfiGraphicsOutputProtocolQueryMode,
+    set_mode: EfiGraphicsOutputProtocolSetMode,
+    blt: EfiGraphicsOutputProtocolBlt,
     pub mode: &'a EfiGraphicsOutputProtocolMode<'a>,
 }
+const _: () = assert!(
+    offset_of!(EfiGraphicsOutputProtocol, mode) == 24
+);
+
+#[derive(Debug, Clone, Copy)]
+struct ModeInfo {
+    pub mode_number: u32,
+    pub info: EfiGraphicsOutputProtocolPixelInfo,
+}
+
+impl<'a> EfiGraphicsOutputProtocol<'a> {
+    fn modes(
+        &self,
+    ) -> impl Iterator<Item = ModeInfo> + use<'_, 'a> {
+        (0..self.mode.max_mode).filter_map(move |mode_number| {
+            let mut size_of_info: u64 = 0;
+            let mut info = null_mut::<EfiGraphicsOutputProtocolPixelInfo>()
+                as *const EfiGraphicsOutputProtocolPixelInfo;
+            let status = (self.query_mode)(
+                self as *const Self as *const EfiVoid,
+                mode_number,
+                &mut size_of_info,
+                &mut info,
+            );
+            if status != EfiStatus::Success || info.is_null() {
+                return None;
+            }
+            Some(ModeInfo {
+                mode_number,
+                info: unsafe { *info },
+            })
+        })
+    }
+
+    fn set_preferred_mode(
+        &self,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        let mode = self
+            .modes()
+            .find(|m| {
+                m.info.horizontal_resolution == width
+                    && m.info.vertical_resolution == height
+            })
+            .ok_or("No matching mode found")?;
+        let status = (self.set_mode)(
+            self as *const Self as *const EfiVoid,
+            mode.mode_number,
+        );
+        if status != EfiStatus::Success {
+            return Err("Failed to set mode");
+        }
+        Ok(())
+    }
+}
+
+// Draws through GOP's Blt entry point instead of the linear
+// framebuffer; the only option on PixelBltOnly modes.
+struct BltBitmap<'a> {
+    gop: &'a EfiGraphicsOutputProtocol<'a>,
+    width: u32,
+    height: u32,
+}
+
+impl<'a> BltBitmap<'a> {
+    fn new(gop: &'a EfiGraphicsOutputProtocol<'a>) -> Self {
+        Self {
+            gop,
+            width: gop.mode.info.horizontal_resolution,
+            height: gop.mode.info.vertical_resolution,
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn fill_rect_blt(
+        &self,
+        color: EfiGraphicsOutputBltPixel,
+        x: u32,
+        y: u32,
+        w: u32,
+        h: u32,
+    ) -> Result<()> {
+        let mut pixel = color;
+        let status = (self.gop.blt)(
+            self.gop as *const EfiGraphicsOutputProtocol
+                as *const EfiVoid,
+            &mut pixel,
+            EfiGraphicsOutputProtocolBltOperation::VideoFill,
+            0,
+            0,
+            x as usize,
+            y as usize,
+            w as usize,
+            h as usize,
+            0,
+        );
+        if status != EfiStatus::Success {
+            return Err("Blt VideoFill failed");
+        }
+        Ok(())
+    }
+
+    fn copy_rect(
+        &self,
+        src_x: u32,
+        src_y: u32,
+        dst_x: u32,
+        dst_y: u32,
+        w: u32,
+        h: u32,
+    ) -> Result<()> {
+        let status = (self.gop.blt)(
+            self.gop as *const EfiGraphicsOutputProtocol
+                as *const EfiVoid,
+            null_mut(),
+            EfiGraphicsOutputProtocolBltOperation::VideoToVideo,
+            src_x as usize,
+            src_y as usize,
+            dst_x as usize,
+            dst_y as usize,
+            w as usize,
+            h as usize,
+            0,
+        );
+        if status != EfiStatus::Success {
+            return Err("Blt VideoToVideo failed");
+        }
+        Ok(())
+    }
+}
+
+fn rgb_to_blt_pixel(rgb: u32) -> EfiGraphicsOutputBltPixel {
+    EfiGraphicsOutputBltPixel {
+        blue: (rgb & 0xff) as u8,
+        green: ((rgb >> 8) & 0xff) as u8,
+        red: ((rgb >> 16) & 0xff) as u8,
+        reserved: 0,
+    }
+}
 
 #[repr(C)]
 #[derive(Debug)]
@@ -120,13 +457,37 @@ struct EfiGraphicsOutputProtocolMode<'a> {
     pub frame_buffer_size: usize,
 }
 
+// Variants are only ever produced by reinterpreting the raw
+// u32 firmware hands back in `pixel_format`, never constructed
+// in safe Rust, so rustc can't see most of them get used.
+#[allow(dead_code)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u32)]
+enum EfiGraphicsOutputProtocolPixelFormat {
+    PixelRedGreenBlueReserved8BitPerColor = 0,
+    PixelBlueGreenRedReserved8BitPerColor = 1,
+    PixelBitMask = 2,
+    PixelBltOnly = 3,
+    PixelFormatMax = 4,
+}
+
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+struct EfiPixelBitmask {
+    pub red_mask: u32,
+    pub green_mask: u32,
+    pub blue_mask: u32,
+    pub reserved_mask: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
 struct EfiGraphicsOutputProtocolPixelInfo {
     version: u32,
     pub horizontal_resolution: u32,
     pub vertical_resolution: u32,
-    _padding: [u32; 5],
+    pub pixel_format: EfiGraphicsOutputProtocolPixelFormat,
+    pub pixel_information: EfiPixelBitmask,
     pub pixels_per_scan_line: u32,
 }
 const _: () = assert!(
@@ -154,12 +515,67 @@ fn locate_graphic_protocol<'a>(
     Ok(unsafe { &*graphics_output_protocol })
 }
 
+fn write_text_status(
+    efi_system_table: &EfiSystemTable,
+    message: &str,
+) {
+    let mut buf = [0u16; 128];
+    let mut len = 0;
+    for c in message.encode_utf16() {
+        if len >= buf.len() - 1 {
+            break;
+        }
+        buf[len] = c;
+        len += 1;
+    }
+    buf[len] = 0;
+
+    let con_out = efi_system_table.con_out;
+    let _ = (con_out.output_string)(
+        con_out as *const EfiSimpleTextOutputProtocol
+            as *const EfiVoid,
+        buf.as_ptr(),
+    );
+}
+
+enum Display {
+    Gop(VramBufferInfo),
+    Blt(BltBitmap<'static>),
+    TextOnly,
+}
+
 trait Bitmap {
     fn bytes_per_pixel(&self) -> u32;
     fn pixels_per_line(&self) -> u32;
     fn width(&self) -> u32;
     fn height(&self) -> u32;
     fn buf_mut(&mut self) -> *mut u8;
+    fn pixel_format(
+        &self,
+    ) -> EfiGraphicsOutputProtocolPixelFormat;
+    fn pixel_bitmask(&self) -> EfiPixelBitmask;
+
+    fn encode_color(&self, rgb: u32) -> u32 {
+        use EfiGraphicsOutputProtocolPixelFormat::*;
+        let r = (rgb >> 16) & 0xff;
+        let g = (rgb >> 8) & 0xff;
+        let b = rgb & 0xff;
+        match self.pixel_format() {
+            PixelRedGreenBlueReserved8BitPerColor => {
+                (r << 16) | (g << 8) | b
+            }
+            PixelBlueGreenRedReserved8BitPerColor => {
+                (b << 16) | (g << 8) | r
+            }
+            PixelBitMask => {
+                let mask = self.pixel_bitmask();
+                encode_channel(r, mask.red_mask)
+                    | encode_channel(g, mask.green_mask)
+                    | encode_channel(b, mask.blue_mask)
+            }
+            _ => rgb,
+        }
+    }
 
     unsafe fn unchecked_pixel_at_mut(
         &mut self,
@@ -198,12 +614,24 @@ trait Bitmap {
     }
 }
 
+fn encode_channel(channel: u32, mask: u32) -> u32 {
+    if mask == 0 {
+        return 0;
+    }
+    let shift = mask.trailing_zeros();
+    let width = mask.count_ones();
+    let max = (1u32 << width) - 1;
+    ((channel * max) / 0xff) << shift
+}
+
 #[derive(Clone, Copy)]
 struct VramBufferInfo {
     buf: *mut u8,
     width: u32,
     height: u32,
     pixels_per_line: u32,
+    pixel_format: EfiGraphicsOutputProtocolPixelFormat,
+    pixel_bitmask: EfiPixelBitmask,
 }
 
 impl Bitmap for VramBufferInfo {
@@ -226,19 +654,55 @@ impl Bitmap for VramBufferInfo {
     fn buf_mut(&mut self) -> *mut u8 {
         self.buf
     }
+
+    fn pixel_format(
+        &self,
+    ) -> EfiGraphicsOutputProtocolPixelFormat {
+        self.pixel_format
+    }
+
+    fn pixel_bitmask(&self) -> EfiPixelBitmask {
+        self.pixel_bitmask
+    }
 }
 
 fn init_vram(
     efi_system_table: &EfiSystemTable,
-) -> Result<VramBufferInfo> {
-    let gp = locate_graphic_protocol(efi_system_table)?;
+    preferred_resolution: Option<(u32, u32)>,
+) -> Result<Display> {
+    enter_graphics_mode(efi_system_table);
+
+    let gp = match locate_graphic_protocol(efi_system_table) {
+        Ok(gp) => gp,
+        Err(_) => {
+            write_text_status(
+                efi_system_table,
+                "No Graphics Output Protocol; continuing in text-only mode.\r\n",
+            );
+            return Ok(Display::TextOnly);
+        }
+    };
+
+    if let Some((width, height)) = preferred_resolution {
+        gp.set_preferred_mode(width, height)?;
+    }
+
+    if gp.mode.info.pixel_format
+        == EfiGraphicsOutputProtocolPixelFormat::PixelBltOnly
+    {
+        // No directly addressable linear framebuffer on this
+        // mode; Blt is the only way to draw to it.
+        return Ok(Display::Blt(BltBitmap::new(gp)));
+    }
 
-    Ok(VramBufferInfo {
+    Ok(Display::Gop(VramBufferInfo {
         buf: gp.mode.frame_buffer_base as *mut u8,
         width: gp.mode.info.horizontal_resolution,
         height: gp.mode.info.vertical_resolution,
         pixels_per_line: gp.mode.info.pixels_per_scan_line,
-    })
+        pixel_format: gp.mode.info.pixel_format,
+        pixel_bitmask: gp.mode.info.pixel_information,
+    }))
 }
 
 unsafe fn unchecked_draw_point<T: Bitmap>(
@@ -256,6 +720,7 @@ fn draw_point<T: Bitmap>(
     x: u32,
     y: u32,
 ) -> Result<()> {
+    let color = buf.encode_color(color);
     *(buf.pixel_at_mut(x, y).ok_or("Out of bounds")?) =
         color;
     Ok(())
@@ -277,6 +742,7 @@ fn fill_rect<T: Bitmap>(
         return Err("Out of bounds");
     }
 
+    let color = buf.encode_color(color);
     for i in 0..h {
         for j in 0..w {
             unsafe {